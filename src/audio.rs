@@ -0,0 +1,273 @@
+//! PCM format decoding and sample-rate conversion shared by every audio
+//! input path (`--stdin`, `--mic`).
+//!
+//! Streaming used to hard-assume 16kHz mono s16le; this module decodes the
+//! broader set of raw PCM encodings the CLI now accepts (mirroring the
+//! format set exposed by the Fuchsia audio facade) and resamples/downmixes
+//! everything down to the 16kHz mono f32 the model requires.
+
+use clap::ValueEnum;
+use std::io::{self, Read};
+
+/// Raw PCM sample encoding of the input stream.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Unsigned 8-bit PCM, centered at 128
+    U8,
+    /// Signed 16-bit little-endian PCM
+    S16le,
+    /// Signed 24-bit little-endian PCM, packed as 3 bytes per sample
+    S24le,
+    /// Signed 32-bit little-endian PCM
+    S32le,
+    /// 32-bit little-endian float PCM
+    F32le,
+}
+
+impl SampleFormat {
+    /// Number of bytes occupied by one sample of this format.
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleFormat::U8 => 1,
+            SampleFormat::S16le => 2,
+            SampleFormat::S24le => 3,
+            SampleFormat::S32le | SampleFormat::F32le => 4,
+        }
+    }
+}
+
+/// Decode a buffer of raw PCM bytes (interleaved channels, whole number of
+/// samples) into f32 samples in `[-1.0, 1.0]`.
+pub fn decode_to_f32(bytes: &[u8], format: SampleFormat) -> Vec<f32> {
+    match format {
+        SampleFormat::U8 => bytes.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        SampleFormat::S16le => bytes
+            .chunks_exact(2)
+            .map(|c| i16::from_le_bytes([c[0], c[1]]) as f32 / 32_768.0)
+            .collect(),
+        SampleFormat::S24le => bytes
+            .chunks_exact(3)
+            .map(|c| {
+                // Sign-extend the 24-bit little-endian value into an i32
+                // (note this is the packed 3-byte form, not 24-in-32).
+                let unsigned = (c[2] as i32) << 16 | (c[1] as i32) << 8 | c[0] as i32;
+                let signed = (unsigned << 8) >> 8;
+                signed as f32 / 8_388_608.0
+            })
+            .collect(),
+        SampleFormat::S32le => bytes
+            .chunks_exact(4)
+            .map(|c| i32::from_le_bytes([c[0], c[1], c[2], c[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        SampleFormat::F32le => bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    }
+}
+
+/// Size (in bytes) of a ~0.25s read buffer for the given PCM format, rounded
+/// down to a whole number of frames.
+pub fn read_buf_size(format: SampleFormat, sample_rate: u32, channels: usize) -> usize {
+    let frames_per_read = (sample_rate / 4).max(1) as usize;
+    frames_per_read * format.bytes_per_sample() * channels
+}
+
+/// Decodes a raw PCM byte stream into 16kHz mono f32 chunks across
+/// successive reads. Raw reads routinely land mid-frame (pipes and TCP
+/// segments don't respect sample boundaries), so this carries the
+/// undecoded remainder between calls, and it carries the resampler's
+/// filter history and fractional phase so chunk boundaries don't produce a
+/// discontinuity. Shared by the `--stdin` and `--serve` input paths.
+pub struct PcmDecoder {
+    format: SampleFormat,
+    sample_rate: u32,
+    channels: usize,
+    /// Bytes read but not yet decoded because they didn't complete a frame.
+    leftover: Vec<u8>,
+    resampler: Resampler,
+}
+
+impl PcmDecoder {
+    pub fn new(format: SampleFormat, sample_rate: u32, channels: usize) -> Self {
+        Self {
+            format,
+            sample_rate,
+            channels,
+            leftover: Vec::new(),
+            resampler: Resampler::new(sample_rate, 16_000),
+        }
+    }
+
+    /// Read one chunk of raw PCM from `reader`, decode it, downmix to mono,
+    /// and resample to 16kHz. Returns `Ok(None)` at EOF. `buf` is reused
+    /// across calls as scratch space for the raw bytes.
+    pub fn read_chunk(&mut self, reader: &mut impl Read, buf: &mut [u8]) -> io::Result<Option<Vec<f32>>> {
+        let frame_bytes = (self.format.bytes_per_sample() * self.channels).max(1);
+
+        let bytes_read = loop {
+            match reader.read(buf) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+
+        self.leftover.extend_from_slice(&buf[..bytes_read]);
+        let usable = self.leftover.len() - (self.leftover.len() % frame_bytes);
+        let decoded = decode_to_f32(&self.leftover[..usable], self.format);
+        self.leftover.drain(..usable);
+
+        let mono = downmix_to_mono(&decoded, self.channels);
+        Ok(Some(self.resampler.push(&mono)))
+    }
+}
+
+/// Downmix interleaved multichannel samples to mono by averaging channels.
+pub fn downmix_to_mono(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Number of taps on each side of the windowed-sinc kernel used by
+/// [`Resampler`] and [`resample`].
+const HALF_TAPS: isize = 8;
+
+/// One-shot windowed-sinc resample of a complete, self-contained buffer
+/// (no state carried in or out), for when the whole signal is already in
+/// memory. Streaming callers that see
+/// the signal arrive in chunks (e.g. `dsp::Denoiser`'s up/downsample passes)
+/// should use [`Resampler`] instead, so filter history and fractional phase
+/// carry across chunk boundaries.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    let mut r = Resampler::new(from_rate, to_rate);
+    let mut out = r.push(samples);
+    out.extend(r.flush());
+    out
+}
+
+/// Windowed-sinc resampler from `from_rate` to `to_rate` that carries
+/// filter history and fractional source position across calls to `push`,
+/// so resampling a signal in chunks gives the same result as resampling it
+/// all at once. Lowers the sinc lowpass cutoff to the target rate's Nyquist
+/// frequency when downsampling to reject aliasing. Call `flush` once at end-of-stream to emit the trailing
+/// samples that were held back for interpolation context.
+pub struct Resampler {
+    from_rate: u32,
+    to_rate: u32,
+    /// Source samples not yet fully consumed: the unconsumed tail of
+    /// previous `push` calls (kept for interpolation context) plus
+    /// whatever's newly arrived.
+    pending: Vec<f32>,
+    /// Fractional source position of the next output sample, relative to
+    /// the start of `pending`.
+    pos: f64,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self { from_rate, to_rate, pending: Vec::new(), pos: 0.0 }
+    }
+
+    /// Resample newly-arrived `samples`, appending them to any held-back
+    /// tail from the previous call.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend_from_slice(samples);
+        self.drain(false)
+    }
+
+    /// Emit the trailing samples held back for interpolation context; taps
+    /// past the end of the stream are left out and the kernel is
+    /// renormalized over the remaining ones. Call once at
+    /// EOF; the resampler is empty afterwards.
+    pub fn flush(&mut self) -> Vec<f32> {
+        self.drain(true)
+    }
+
+    fn drain(&mut self, at_eof: bool) -> Vec<f32> {
+        if self.from_rate == self.to_rate {
+            self.pos = 0.0;
+            return std::mem::take(&mut self.pending);
+        }
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+
+        let ratio = self.from_rate as f64 / self.to_rate as f64;
+        let cutoff = if ratio > 1.0 { 1.0 / ratio } else { 1.0 };
+
+        let mut out = Vec::new();
+        while self.pos < self.pending.len() as f64 {
+            let center = self.pos.floor() as isize;
+            // Without the full window's worth of future samples, hold this
+            // (and every later) output back for the next call.
+            if !at_eof && (center + HALF_TAPS) as usize >= self.pending.len() {
+                break;
+            }
+            out.push(interpolate(&self.pending, self.pos, cutoff));
+            self.pos += ratio;
+        }
+
+        if at_eof {
+            self.pending.clear();
+            self.pos = 0.0;
+        } else {
+            // Drop everything before the window needed for the next
+            // output sample, keeping `pos` relative to the new start.
+            let keep_from = ((self.pos.floor() as isize) - HALF_TAPS).max(0) as usize;
+            if keep_from > 0 {
+                self.pending.drain(..keep_from);
+                self.pos -= keep_from as f64;
+            }
+        }
+
+        out
+    }
+}
+
+/// Windowed-sinc interpolation of the sample at fractional position
+/// `src_pos` within `samples`, using `HALF_TAPS` taps on each side.
+fn interpolate(samples: &[f32], src_pos: f64, cutoff: f64) -> f32 {
+    let center = src_pos.floor() as isize;
+    let mut acc = 0.0f64;
+    let mut norm = 0.0f64;
+    for tap in -HALF_TAPS..=HALF_TAPS {
+        let idx = center + tap;
+        if idx < 0 || idx as usize >= samples.len() {
+            continue;
+        }
+        let x = src_pos - idx as f64;
+        let w = sinc(x * cutoff) * cutoff * hann_window(x, HALF_TAPS as f64);
+        acc += samples[idx as usize] as f64 * w;
+        norm += w;
+    }
+    if norm.abs() > 1e-9 {
+        (acc / norm) as f32
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x)
+    }
+}
+
+fn hann_window(x: f64, half_width: f64) -> f64 {
+    if x.abs() >= half_width {
+        0.0
+    } else {
+        0.5 + 0.5 * (std::f64::consts::PI * x / half_width).cos()
+    }
+}
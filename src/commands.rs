@@ -0,0 +1,81 @@
+//! `--commands <file>`: match recognized text against a fixed set of spoken
+//! command phrases alongside emitting free transcription.
+//! Mirrors whisper-LSP's "commandset" concept — each line of the file is one
+//! command; on every transcription result we score every command by
+//! normalized token overlap against the recognized text and emit the best
+//! match above `--command-thold` as a structured event.
+
+use serde::Serialize;
+
+/// A single recognized-command event, emitted alongside the `TranscriptEvent`
+/// for the same result when `--commands` is active.
+#[derive(Serialize)]
+pub struct CommandEvent {
+    pub command: String,
+    pub score: f32,
+    pub is_final: bool,
+}
+
+/// A loaded command phrase and its precomputed token sequence.
+struct Command {
+    phrase: String,
+    tokens: Vec<String>,
+}
+
+/// A fixed set of spoken commands to match recognized text against.
+pub struct CommandSet {
+    commands: Vec<Command>,
+    thold: f32,
+}
+
+impl CommandSet {
+    /// Load one command phrase per non-empty line of `path`.
+    pub fn load(path: &std::path::Path, thold: f32) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let commands = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Command { phrase: line.to_string(), tokens: tokenize(line) })
+            .collect();
+        Ok(Self { commands, thold })
+    }
+
+    /// Score every command against `text` and return the best match whose
+    /// score clears `--command-thold`, if any.
+    pub fn best_match(&self, text: &str) -> Option<(&str, f32)> {
+        let recognized = tokenize(text);
+        if recognized.is_empty() {
+            return None;
+        }
+
+        self.commands
+            .iter()
+            .map(|cmd| (cmd.phrase.as_str(), token_overlap_score(&cmd.tokens, &recognized)))
+            .filter(|&(_, score)| score >= self.thold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|w| w.to_lowercase()).collect()
+}
+
+/// Normalized token overlap: the fraction of the command's tokens that also
+/// appear in the recognized text, weighted down for extra recognized tokens
+/// the command doesn't account for (so "open settings please" scores lower
+/// against "open settings" than an exact match would).
+fn token_overlap_score(command_tokens: &[String], recognized_tokens: &[String]) -> f32 {
+    if command_tokens.is_empty() {
+        return 0.0;
+    }
+
+    let matched = command_tokens.iter().filter(|t| recognized_tokens.contains(t)).count();
+    let precision = matched as f32 / command_tokens.len() as f32;
+    let recall = matched as f32 / recognized_tokens.len() as f32;
+    if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
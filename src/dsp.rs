@@ -0,0 +1,252 @@
+//! Optional preprocessing applied to the f32 audio buffer before
+//! `transcribe_samples`: RNNoise-style noise suppression (`--denoise`, via
+//! [`Denoiser`]) and EBU R128 / ITU-R BS.1770 loudness normalization
+//! (`--normalize`, via [`apply_normalize`]). Both are no-ops unless their
+//! flag is set, so the default fast path is unchanged.
+
+use std::f64::consts::PI;
+
+/// Apply `--normalize`, or return `samples` unchanged if it's off.
+/// `sample_rate` is the rate of `samples` (always 16kHz for the streaming
+/// engine today).
+pub fn apply_normalize(samples: &[f32], sample_rate: u32, normalize: bool, target_lufs: f32) -> Vec<f32> {
+    if normalize {
+        normalize_loudness(samples, sample_rate, target_lufs)
+    } else {
+        samples.to_vec()
+    }
+}
+
+// ── Noise suppression ──────────────────────────────────────────────
+
+/// RNNoise operates on 480-sample (10ms) frames of i16-range audio at
+/// 48kHz, so we resample up, denoise frame-by-frame, and resample back.
+///
+/// Incremental: `push` denoises only newly-arrived samples, carrying the
+/// recurrent RNNoise state and both resamplers' filter history/phase
+/// across calls, so a long utterance doesn't pay an O(n) re-denoise (and
+/// cold-started recurrent state) on every streaming tick. Call `flush`
+/// once the utterance ends to emit the last partial frame.
+pub struct Denoiser {
+    up: crate::audio::Resampler,
+    down: crate::audio::Resampler,
+    state: Box<nnnoiseless::DenoiseState<'static>>,
+    /// Upsampled (48kHz) samples not yet enough to fill a full RNNoise frame.
+    pending: Vec<f32>,
+}
+
+const RNNOISE_RATE: u32 = 48_000;
+const FRAME_SIZE: usize = nnnoiseless::DenoiseState::FRAME_SIZE;
+
+impl Denoiser {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            up: crate::audio::Resampler::new(sample_rate, RNNOISE_RATE),
+            down: crate::audio::Resampler::new(RNNOISE_RATE, sample_rate),
+            state: nnnoiseless::DenoiseState::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Denoise newly-arrived `samples` (at this denoiser's `sample_rate`),
+    /// returning as many denoised samples as a whole number of RNNoise
+    /// frames allows; the remainder is carried to the next call.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend(self.up.push(samples));
+        let denoised_48k = self.process_whole_frames();
+        self.down.push(&denoised_48k)
+    }
+
+    /// Flush the resamplers and denoise the final partial frame (zero-padded,
+    /// like RNNoise's own end-of-stream handling) once the utterance ends.
+    pub fn flush(&mut self) -> Vec<f32> {
+        self.pending.extend(self.up.flush());
+        let mut denoised_48k = self.process_whole_frames();
+
+        if !self.pending.is_empty() {
+            let mut frame_in = [0.0f32; FRAME_SIZE];
+            let mut frame_out = [0.0f32; FRAME_SIZE];
+            for (dst, &src) in frame_in.iter_mut().zip(self.pending.iter()) {
+                *dst = src * 32_768.0;
+            }
+            self.state.process_frame(&mut frame_out, &frame_in);
+            denoised_48k.extend(frame_out[..self.pending.len()].iter().map(|&s| s / 32_768.0));
+            self.pending.clear();
+        }
+
+        let mut out = self.down.push(&denoised_48k);
+        out.extend(self.down.flush());
+        out
+    }
+
+    fn process_whole_frames(&mut self) -> Vec<f32> {
+        let mut denoised = Vec::new();
+        let mut frame_in = [0.0f32; FRAME_SIZE];
+        let mut frame_out = [0.0f32; FRAME_SIZE];
+        let mut consumed = 0;
+        while self.pending.len() - consumed >= FRAME_SIZE {
+            // nnnoiseless (like RNNoise) expects samples scaled to i16 range.
+            for (dst, &src) in frame_in.iter_mut().zip(&self.pending[consumed..consumed + FRAME_SIZE]) {
+                *dst = src * 32_768.0;
+            }
+            self.state.process_frame(&mut frame_out, &frame_in);
+            denoised.extend(frame_out.iter().map(|&s| s / 32_768.0));
+            consumed += FRAME_SIZE;
+        }
+        self.pending.drain(..consumed);
+        denoised
+    }
+}
+
+// ── Loudness normalization (EBU R128 / ITU-R BS.1770) ──────────────
+
+/// Measure integrated loudness and apply a flat gain to hit `target_lufs`.
+fn normalize_loudness(samples: &[f32], sample_rate: u32, target_lufs: f32) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+
+    let measured = integrated_loudness_lufs(samples, sample_rate);
+    if !measured.is_finite() {
+        return samples.to_vec();
+    }
+
+    let gain = 10f32.powf((target_lufs - measured) / 20.0);
+    samples.iter().map(|&s| (s * gain).clamp(-1.0, 1.0)).collect()
+}
+
+/// ITU-R BS.1770 integrated loudness: K-weight, measure mean square over
+/// 400ms blocks (100ms hop), then apply the absolute (-70 LUFS) and
+/// relative (-10 LU below ungated loudness) gates.
+fn integrated_loudness_lufs(samples: &[f32], sample_rate: u32) -> f32 {
+    let weighted = k_weight(samples, sample_rate);
+
+    let block_len = (sample_rate as f64 * 0.4) as usize;
+    let hop = (sample_rate as f64 * 0.1).max(1.0) as usize;
+    if block_len == 0 || weighted.len() < block_len {
+        return loudness_from_mean_square(mean_square(&weighted));
+    }
+
+    let mut block_loudness = Vec::new();
+    let mut pos = 0;
+    while pos + block_len <= weighted.len() {
+        block_loudness.push(loudness_from_mean_square(mean_square(&weighted[pos..pos + block_len])));
+        pos += hop;
+    }
+
+    // Absolute gate: discard blocks below -70 LUFS
+    let absolute: Vec<f32> = block_loudness.into_iter().filter(|&l| l > -70.0).collect();
+    if absolute.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let ungated_ms: f64 = absolute.iter().map(|&l| mean_square_from_loudness(l)).sum::<f64>() / absolute.len() as f64;
+    let ungated_loudness = loudness_from_mean_square(ungated_ms);
+
+    // Relative gate: discard blocks more than 10 LU below the ungated loudness
+    let relative_thold = ungated_loudness - 10.0;
+    let gated: Vec<f64> = absolute
+        .into_iter()
+        .filter(|&l| l > relative_thold)
+        .map(mean_square_from_loudness)
+        .collect();
+    if gated.is_empty() {
+        return ungated_loudness;
+    }
+
+    loudness_from_mean_square(gated.iter().sum::<f64>() / gated.len() as f64)
+}
+
+fn mean_square(samples: &[f32]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64
+}
+
+fn loudness_from_mean_square(ms: f64) -> f32 {
+    (-0.691 + 10.0 * ms.log10()) as f32
+}
+
+fn mean_square_from_loudness(l: f32) -> f64 {
+    10f64.powf((l as f64 + 0.691) / 10.0)
+}
+
+/// K-weighting pre-filter: a head-effects high-shelf stage followed by the
+/// RLB high-pass stage, per BS.1770-4 Annex 1. The analog prototype
+/// parameters (f0/Q/gain) are sample-rate independent; coefficients are
+/// re-derived per `sample_rate` via the standard biquad bilinear transform.
+fn k_weight(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let fs = sample_rate as f64;
+    let stage1 = Biquad::high_shelf(1681.974_450_955_531_9, fs, 0.707_175_236_955_419_3, 3.999_843_853_97);
+    let stage2 = Biquad::high_pass(38.135_470_876_139_82, fs, 0.500_327_037_325_395_3);
+
+    let mut out = Vec::with_capacity(samples.len());
+    let mut s1 = stage1;
+    let mut s2 = stage2;
+    for &x in samples {
+        out.push(s2.process(s1.process(x as f64)) as f32);
+    }
+    out
+}
+
+/// Direct-form-I biquad filter.
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self { b0, b1, b2, a1, a2, x1: 0.0, x2: 0.0, y1: 0.0, y2: 0.0 }
+    }
+
+    /// RBJ Audio EQ Cookbook high-shelf design.
+    fn high_shelf(f0: f64, fs: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * f0 / fs;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let b0 = a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha);
+        let b1 = -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0);
+        let b2 = a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha);
+        let a0 = (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha;
+        let a1 = 2.0 * ((a - 1.0) - (a + 1.0) * cos_w0);
+        let a2 = (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha;
+
+        Self::new(b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0)
+    }
+
+    /// RBJ Audio EQ Cookbook high-pass design.
+    fn high_pass(f0: f64, fs: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * f0 / fs;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+
+        let b0 = (1.0 + cos_w0) / 2.0;
+        let b1 = -(1.0 + cos_w0);
+        let b2 = (1.0 + cos_w0) / 2.0;
+        let a0 = 1.0 + alpha;
+        let a1 = -2.0 * cos_w0;
+        let a2 = 1.0 - alpha;
+
+        Self::new(b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0)
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
@@ -4,18 +4,32 @@
  * Thin CLI wrapper around the parakeet-rs library for local
  * speech-to-text transcription using NVIDIA Parakeet TDT.
  *
- * Supports two modes:
+ * Supports four modes:
  *   1. Batch: parakeet-cli --model-dir <path> --input <file.wav>
  *   2. Streaming (stdin): parakeet-cli --model-dir <path> --stdin
+ *   3. Streaming (microphone): parakeet-cli --model-dir <path> --mic
+ *   4. Server (TCP): parakeet-cli --model-dir <path> --serve <addr>
  *
- * In streaming mode, audio is read from stdin as raw 16kHz mono s16le PCM.
- * Partial transcription results are emitted every ~0.5 seconds of audio
- * as JSONL on stdout, enabling real-time live preview.
+ * In streaming mode, audio is read from stdin as raw PCM (16kHz mono s16le
+ * by default; `--sample-rate`, `--channels`, and `--sample-format` accept
+ * other encodings), or (with `--mic`) captured directly from an input
+ * device via cpal — both paths are decoded, downmixed, and resampled down
+ * to 16kHz mono before transcription. Partial transcription results are
+ * emitted every ~0.5 seconds of audio as JSONL on stdout, enabling
+ * real-time live preview. With `--vad`, an energy-based voice-activity
+ * detector additionally endpoints each utterance: once trailing silence
+ * holds for `--silence-ms`, a final result is emitted immediately and the
+ * buffer resets for the next one.
  *
  * Output is JSONL on stdout:
  *   {"text":"Hello","is_final":false,"audio_duration_secs":2.1}
  *   {"text":"Hello world.","is_final":false,"audio_duration_secs":4.3}
  *   {"text":"Hello world. How are you?","is_final":true,"duration_secs":0.12}
+ *
+ * With `--commands <file>`, each result is additionally scored against a
+ * fixed set of command phrases (one per line of `<file>`) and a match above
+ * `--command-thold` is emitted alongside the transcript:
+ *   {"command":"open settings","score":0.92,"is_final":true}
  */
 use clap::Parser;
 use parakeet_rs::{ParakeetTDT, TimestampMode, Transcriber};
@@ -24,19 +38,14 @@ use std::io::{self, Read, Write};
 use std::path::PathBuf;
 use std::time::Instant;
 
-/// Minimum audio duration (in samples at 16kHz) before first transcription.
-/// ~1 second — shorter audio tends to produce garbage.
-const MIN_SAMPLES_FOR_TRANSCRIPTION: usize = 16_000;
-
-/// How often to run intermediate transcription (in samples at 16kHz).
-/// ~0.5 seconds of audio between each partial result.
-/// At ~40-80ms inference on M-series chips, this gives near real-time feel
-/// with text updating roughly twice per second.
-const CHUNK_INTERVAL_SAMPLES: usize = 8_000;
-
-/// Maximum audio buffer size (in samples at 16kHz).
-/// ~3 minutes — TDT models have a ~4-5 min hard limit.
-const MAX_BUFFER_SAMPLES: usize = 16_000 * 180;
+mod audio;
+mod commands;
+mod dsp;
+mod mic;
+mod serve;
+mod stream;
+mod transport;
+mod vad;
 
 #[derive(Parser, Debug)]
 #[command(name = "parakeet-cli", version, about = "Speech-to-text using NVIDIA Parakeet TDT")]
@@ -53,9 +62,96 @@ struct Args {
     #[arg(long)]
     stdin: bool,
 
+    /// Capture audio directly from a microphone via cpal (streaming mode),
+    /// instead of piping raw PCM into --stdin
+    #[arg(long)]
+    mic: bool,
+
+    /// Name of the input device to use with --mic (defaults to the system
+    /// default input device). See --list-devices for available names
+    #[arg(long)]
+    device: Option<String>,
+
+    /// List available microphone input devices and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Run as a TCP server on `<addr>` (e.g. 0.0.0.0:7000): each connection
+    /// streams raw PCM in and gets JSONL transcripts back, sharing one
+    /// loaded model across connections
+    #[arg(long, value_name = "addr")]
+    serve: Option<String>,
+
+    /// Shared key enabling a symmetric XOR stream cipher over --serve
+    /// connections (lightweight obfuscation, not real encryption)
+    #[arg(long)]
+    key: Option<String>,
+
+    /// Sample rate (Hz) of raw PCM given to --stdin or --serve; resampled to
+    /// the model's required 16kHz
+    #[arg(long, default_value_t = 16_000)]
+    sample_rate: u32,
+
+    /// Number of interleaved channels in raw PCM given to --stdin or
+    /// --serve; downmixed to mono by averaging
+    #[arg(long, default_value_t = 1)]
+    channels: usize,
+
+    /// Sample encoding of raw PCM given to --stdin or --serve
+    #[arg(long, value_enum, default_value = "s16le")]
+    sample_format: audio::SampleFormat,
+
     /// Output format: "json" for JSONL, "text" for plain text
     #[arg(long, default_value = "json")]
     format: String,
+
+    /// Enable energy-based voice-activity endpointing in the streaming modes
+    /// (--stdin, --mic, --serve): emit
+    /// an `is_final` result at each detected end-of-utterance instead of
+    /// only at EOF, and reset the buffer afterwards
+    #[arg(long)]
+    vad: bool,
+
+    /// VAD speech/silence energy ratio threshold (trailing window energy
+    /// must exceed this fraction of the whole-buffer energy to count as speech)
+    #[arg(long, default_value_t = 0.6)]
+    vad_thold: f32,
+
+    /// High-pass filter cutoff in Hz applied before VAD energy analysis, to
+    /// reject low-frequency rumble. Set to 0 to disable the filter
+    #[arg(long, default_value_t = 100.0)]
+    freq_thold: f32,
+
+    /// Trailing silence duration (ms) after speech before an utterance is
+    /// considered ended and a final transcription is emitted
+    #[arg(long, default_value_t = 500)]
+    silence_ms: u64,
+
+    /// Run RNNoise-style noise suppression on streamed audio before
+    /// transcription
+    #[arg(long)]
+    denoise: bool,
+
+    /// Normalize streamed audio to a target EBU R128 / ITU-R BS.1770
+    /// loudness before transcription
+    #[arg(long)]
+    normalize: bool,
+
+    /// Target integrated loudness in LUFS for --normalize
+    #[arg(long, default_value_t = -23.0)]
+    target_lufs: f32,
+
+    /// Path to a file listing one spoken command phrase per line. When set,
+    /// every transcription result is also scored against this command set
+    /// and a matching `--command-thold`-or-better result is emitted as a
+    /// command event alongside the regular transcript
+    #[arg(long)]
+    commands: Option<PathBuf>,
+
+    /// Minimum command-match score (normalized token overlap, 0.0-1.0) for
+    /// --commands to emit a command event
+    #[arg(long, default_value_t = 0.5)]
+    command_thold: f32,
 }
 
 #[derive(Serialize)]
@@ -68,6 +164,10 @@ struct TranscriptEvent {
     audio_duration_secs: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     timestamps: Option<Vec<TimedTokenOut>>,
+    /// Wall-clock capture time (Unix epoch seconds) of the audio this event
+    /// covers, when the source can provide one (currently only --mic)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    captured_at: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -77,27 +177,69 @@ struct TimedTokenOut {
     end: f32,
 }
 
+/// An output event: either a free-transcription result, or (with
+/// `--commands`) a recognized command match. Each serializes to its own flat
+/// JSON shape rather than a tagged union, since consumers key off whichever
+/// fields are present.
+enum Event {
+    Transcript(TranscriptEvent),
+    Command(commands::CommandEvent),
+}
+
 /// Emit a JSONL event to stdout and flush immediately.
-fn emit_event(event: &TranscriptEvent, format: &str) {
-    if format == "text" {
-        println!("{}", event.text);
-    } else {
-        if let Ok(json) = serde_json::to_string(event) {
-            println!("{}", json);
+fn emit_event(event: &Event, format: &str) {
+    let mut writer = transport::Writer::Stdout(io::stdout());
+    emit_event_to(&mut writer, event, format);
+}
+
+/// Write a single event to `writer` and flush immediately, so partial
+/// results reach the consumer without delay. Used for stdout in the default
+/// case and for per-connection sockets in `--serve` mode.
+fn emit_event_to(writer: &mut impl Write, event: &Event, format: &str) {
+    let line = match event {
+        Event::Transcript(t) => {
+            if format == "text" {
+                format!("{}\n", t.text)
+            } else {
+                match serde_json::to_string(t) {
+                    Ok(json) => format!("{}\n", json),
+                    Err(_) => return,
+                }
+            }
         }
-    }
-    // Flush immediately so the consumer sees partial results without delay
-    let _ = io::stdout().flush();
+        Event::Command(c) => {
+            if format == "text" {
+                format!("{}\n", c.command)
+            } else {
+                match serde_json::to_string(c) {
+                    Ok(json) => format!("{}\n", json),
+                    Err(_) => return,
+                }
+            }
+        }
+    };
+    let _ = writer.write_all(line.as_bytes());
+    let _ = writer.flush();
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
+    if args.list_devices {
+        mic::list_devices()?;
+        return Ok(());
+    }
+
     eprintln!("Loading model from {:?}...", args.model_dir);
     let start = Instant::now();
     let mut model = ParakeetTDT::from_pretrained(&args.model_dir, None)?;
     eprintln!("Model loaded in {:.2}s", start.elapsed().as_secs_f32());
 
+    let commandset = match &args.commands {
+        Some(path) => Some(commands::CommandSet::load(path, args.command_thold)?),
+        None => None,
+    };
+
     if let Some(input_path) = &args.input {
         // ── Batch mode: transcribe a WAV file ──────────────────────
         let start = Instant::now();
@@ -114,8 +256,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             })
             .collect();
 
+        if let Some(cs) = &commandset {
+            if let Some((command, score)) = cs.best_match(&result.text) {
+                emit_event(
+                    &Event::Command(commands::CommandEvent { command: command.to_string(), score, is_final: true }),
+                    &args.format,
+                );
+            }
+        }
+
         emit_event(
-            &TranscriptEvent {
+            &Event::Transcript(TranscriptEvent {
                 text: result.text,
                 is_final: true,
                 duration_secs: Some(elapsed),
@@ -125,172 +276,49 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 } else {
                     Some(timestamps)
                 },
-            },
+                captured_at: None,
+            }),
             &args.format,
         );
     } else if args.stdin {
         // ── Streaming mode: read raw PCM from stdin ────────────────
-        // Input format: 16kHz, mono, signed 16-bit little-endian (s16le)
-        //
-        // Strategy: accumulate audio in a growing buffer. Every ~0.5 seconds
-        // of new audio, re-transcribe the entire buffer and emit a partial
-        // JSONL result. When stdin closes (EOF), emit the final result.
-        // ParakeetTDT is stateless so each transcribe call is independent.
-        let _start = Instant::now();
-        let mut all_audio: Vec<f32> = Vec::new();
-        let mut buf = [0u8; 8000]; // Read in small chunks (0.25s) for responsiveness
-        let mut samples_since_last_transcription: usize = 0;
-        let mut last_text = String::new();
-
-        let stdin_handle = io::stdin();
-        let mut handle = stdin_handle.lock();
-
-        // Emit a "ready" event so the consumer knows audio processing has started
-        emit_event(
-            &TranscriptEvent {
-                text: String::new(),
-                is_final: false,
-                duration_secs: None,
-                audio_duration_secs: Some(0.0),
-                timestamps: None,
-            },
-            &args.format,
-        );
-
-        loop {
-            let bytes_read = match handle.read(&mut buf) {
-                Ok(0) => break, // EOF — sox stopped
-                Ok(n) => n,
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                Err(e) => return Err(e.into()),
-            };
-
-            // Convert s16le bytes to f32 samples
-            let samples: Vec<f32> = buf[..bytes_read]
-                .chunks_exact(2)
-                .map(|chunk| {
-                    let sample = i16::from_le_bytes([chunk[0], chunk[1]]);
-                    sample as f32 / 32768.0
-                })
-                .collect();
-
-            all_audio.extend_from_slice(&samples);
-            samples_since_last_transcription += samples.len();
-
-            // Enforce max buffer size (~3 minutes) — trim from the front
-            if all_audio.len() > MAX_BUFFER_SAMPLES {
-                let excess = all_audio.len() - MAX_BUFFER_SAMPLES;
-                all_audio.drain(..excess);
-                eprintln!(
-                    "Warning: audio buffer exceeded 3 minutes, trimmed oldest {} samples",
-                    excess
-                );
-            }
+        // Input format defaults to 16kHz mono s16le but --sample-rate,
+        // --channels, and --sample-format accept the wider set the CLI now
+        // supports; every format is decoded, downmixed, and resampled to
+        // the model's required 16kHz mono before reaching the shared
+        // engine below. ParakeetTDT is stateless so each transcribe call
+        // in that engine is independent.
+        let mut reader = transport::Reader::Stdin(io::stdin());
+        let mut buf = vec![0u8; audio::read_buf_size(args.sample_format, args.sample_rate, args.channels)];
+        let mut decoder = audio::PcmDecoder::new(args.sample_format, args.sample_rate, args.channels);
 
-            // Emit partial transcription every ~0.5 seconds of new audio,
-            // but only after we have enough audio for a meaningful result
-            if samples_since_last_transcription >= CHUNK_INTERVAL_SAMPLES
-                && all_audio.len() >= MIN_SAMPLES_FOR_TRANSCRIPTION
-            {
-                samples_since_last_transcription = 0;
-                let audio_duration = all_audio.len() as f32 / 16000.0;
-
-                match model.transcribe_samples(
-                    all_audio.clone(),
-                    16000,
-                    1,
-                    None, // Skip timestamps for partial results (faster)
-                ) {
-                    Ok(result) => {
-                        last_text = result.text.clone();
-                        emit_event(
-                            &TranscriptEvent {
-                                text: result.text,
-                                is_final: false,
-                                duration_secs: None,
-                                audio_duration_secs: Some(audio_duration),
-                                timestamps: None,
-                            },
-                            &args.format,
-                        );
-                    }
+        stream::run(
+            &mut model,
+            &args,
+            commandset.as_ref(),
+            || {
+                match decoder.read_chunk(&mut reader, &mut buf) {
+                    Ok(Some(samples)) => Some(stream::AudioChunk { samples, captured_at: None }),
+                    Ok(None) => None, // EOF — sox stopped
                     Err(e) => {
-                        eprintln!("Warning: partial transcription failed: {}", e);
-                        // Continue accumulating audio — don't abort
-                    }
-                }
-            }
-        }
-
-        // ── Final transcription after EOF ──────────────────────────
-        if all_audio.is_empty() {
-            emit_event(
-                &TranscriptEvent {
-                    text: String::new(),
-                    is_final: true,
-                    duration_secs: Some(0.0),
-                    audio_duration_secs: Some(0.0),
-                    timestamps: None,
-                },
-                &args.format,
-            );
-        } else {
-            let final_start = Instant::now();
-            match model.transcribe_samples(
-                all_audio.clone(),
-                16000,
-                1,
-                Some(TimestampMode::Sentences),
-            ) {
-                Ok(result) => {
-                    let elapsed = final_start.elapsed().as_secs_f32();
-                    let audio_duration = all_audio.len() as f32 / 16000.0;
-
-                    let timestamps: Vec<TimedTokenOut> = result
-                        .tokens
-                        .iter()
-                        .map(|t| TimedTokenOut {
-                            word: t.text.clone(),
-                            start: t.start,
-                            end: t.end,
-                        })
-                        .collect();
-
-                    emit_event(
-                        &TranscriptEvent {
-                            text: result.text,
-                            is_final: true,
-                            duration_secs: Some(elapsed),
-                            audio_duration_secs: Some(audio_duration),
-                            timestamps: if timestamps.is_empty() {
-                                None
-                            } else {
-                                Some(timestamps)
-                            },
-                        },
-                        &args.format,
-                    );
-                }
-                Err(e) => {
-                    // If final transcription fails, emit last known good text
-                    eprintln!("Error: final transcription failed: {}", e);
-                    if !last_text.is_empty() {
-                        emit_event(
-                            &TranscriptEvent {
-                                text: last_text,
-                                is_final: true,
-                                duration_secs: None,
-                                audio_duration_secs: None,
-                                timestamps: None,
-                            },
-                            &args.format,
-                        );
+                        eprintln!("Error: failed to read stdin: {}", e);
+                        None
                     }
                 }
-            }
-        }
+            },
+            |event| emit_event(event, &args.format),
+        )?;
+    } else if args.mic {
+        // ── Streaming mode: capture directly from a microphone ─────
+        // The stream must stay alive for the duration of capture; dropping
+        // it (end of this branch) stops the device.
+        let (_cpal_stream, rx) = mic::capture(args.device.as_deref())?;
+        stream::run(&mut model, &args, commandset.as_ref(), || rx.recv().ok(), |event| emit_event(event, &args.format))?;
+    } else if let Some(addr) = &args.serve {
+        // ── Server mode: accept PCM over TCP, stream transcripts back ──
+        serve::run(&mut model, &args, commandset.as_ref(), addr)?;
     } else {
-        eprintln!("Error: specify either --input <file.wav> or --stdin");
+        eprintln!("Error: specify one of --input <file.wav>, --stdin, --mic, or --serve <addr>");
         std::process::exit(1);
     }
 
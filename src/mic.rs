@@ -0,0 +1,126 @@
+//! Direct microphone capture via cpal, feeding the same streaming engine
+//! used by `--stdin` (see [`crate::stream`]).
+//!
+//! cpal hands us whatever format / channel count / sample rate the device
+//! natively supports; each callback is downmixed to mono and resampled to
+//! the model's required 16kHz before being handed to the transcription loop.
+
+use crate::stream::AudioChunk;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, StreamInstant};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// Print the name of every available input device to stdout, one per line.
+pub fn list_devices() -> Result<(), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    for device in host.input_devices()? {
+        println!("{}", device.name()?);
+    }
+    Ok(())
+}
+
+/// Open an input stream on the default (or named) device and return a
+/// receiver of [`AudioChunk`]s already converted to 16kHz mono f32. The
+/// returned `cpal::Stream` must be kept alive for capture to continue —
+/// dropping it stops the device.
+pub fn capture(device_name: Option<&str>) -> Result<(cpal::Stream, Receiver<AudioChunk>), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let device = match device_name {
+        Some(name) => host
+            .input_devices()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| format!("input device not found: {}", name))?,
+        None => host.default_input_device().ok_or("no default input device available")?,
+    };
+
+    let config = device.default_input_config()?;
+    let sample_format = config.sample_format();
+    let channels = config.channels() as usize;
+    let source_rate = config.sample_rate().0;
+
+    eprintln!(
+        "Capturing from {:?} ({} ch, {} Hz, {:?})",
+        device.name().unwrap_or_default(),
+        channels,
+        source_rate,
+        sample_format,
+    );
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stream_config: cpal::StreamConfig = config.into();
+    let anchor: Arc<Mutex<Option<TimeAnchor>>> = Arc::new(Mutex::new(None));
+
+    let stream = match sample_format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &stream_config, channels, source_rate, tx, anchor)?,
+        SampleFormat::I16 => build_stream::<i16>(&device, &stream_config, channels, source_rate, tx, anchor)?,
+        SampleFormat::U16 => build_stream::<u16>(&device, &stream_config, channels, source_rate, tx, anchor)?,
+        other => return Err(format!("unsupported input sample format: {:?}", other).into()),
+    };
+
+    stream.play()?;
+    Ok((stream, rx))
+}
+
+/// Anchors a `StreamInstant` to a wall-clock time so later instants in the
+/// same stream can be converted to Unix epoch seconds via `duration_since`.
+struct TimeAnchor {
+    stream_instant: StreamInstant,
+    wall_time: SystemTime,
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    source_rate: u32,
+    tx: Sender<AudioChunk>,
+    anchor: Arc<Mutex<Option<TimeAnchor>>>,
+) -> Result<cpal::Stream, Box<dyn std::error::Error>>
+where
+    T: cpal::Sample + cpal::SizedSample + Send + 'static,
+    f32: cpal::FromSample<T>,
+{
+    let mut resampler = crate::audio::Resampler::new(source_rate, TARGET_SAMPLE_RATE);
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], info: &cpal::InputCallbackInfo| {
+            let captured_at = to_wall_clock(&anchor, info.timestamp().capture);
+            let mono = downmix_to_mono(data, channels);
+            let resampled = resampler.push(&mono);
+            let _ = tx.send(AudioChunk { samples: resampled, captured_at });
+        },
+        move |err| eprintln!("Warning: microphone stream error: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Convert a `StreamInstant` to Unix epoch seconds, anchoring the stream's
+/// first callback to the wall clock and using `duration_since` for the rest.
+fn to_wall_clock(anchor: &Arc<Mutex<Option<TimeAnchor>>>, instant: StreamInstant) -> Option<f64> {
+    let mut guard = anchor.lock().ok()?;
+    let anchor_ref = guard.get_or_insert_with(|| TimeAnchor {
+        stream_instant: instant,
+        wall_time: SystemTime::now(),
+    });
+    let offset = instant.duration_since(&anchor_ref.stream_instant)?;
+    let wall = anchor_ref.wall_time + offset;
+    wall.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs_f64())
+}
+
+fn downmix_to_mono<T>(data: &[T], channels: usize) -> Vec<f32>
+where
+    T: cpal::Sample + Copy,
+    f32: cpal::FromSample<T>,
+{
+    if channels <= 1 {
+        return data.iter().map(|&s| f32::from_sample(s)).collect();
+    }
+    data.chunks_exact(channels)
+        .map(|frame| frame.iter().map(|&s| f32::from_sample(s)).sum::<f32>() / channels as f32)
+        .collect()
+}
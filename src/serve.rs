@@ -0,0 +1,74 @@
+//! `--serve <addr>`: a long-lived TCP server wrapping the same streaming
+//! engine used by `--stdin`/`--mic`. Each connection reads raw PCM (the
+//! same `--sample-rate`/`--channels`/`--sample-format` flags apply) and
+//! gets JSONL `TranscriptEvent`s written back over the same socket. The
+//! model is loaded once by the caller and reused across connections.
+
+use crate::commands::CommandSet;
+use crate::transport::{Reader, Writer, XorState};
+use crate::{audio, emit_event_to, stream, Args};
+use parakeet_rs::ParakeetTDT;
+use std::net::{TcpListener, TcpStream};
+
+pub fn run(
+    model: &mut ParakeetTDT,
+    args: &Args,
+    commandset: Option<&CommandSet>,
+    addr: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("Listening for PCM connections on {}", addr);
+
+    for conn in listener.incoming() {
+        let conn = match conn {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Warning: failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let peer = conn.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "?".into());
+        eprintln!("Accepted connection from {}", peer);
+        if let Err(e) = handle_connection(model, args, commandset, conn) {
+            eprintln!("Warning: connection {} ended with error: {}", peer, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(
+    model: &mut ParakeetTDT,
+    args: &Args,
+    commandset: Option<&CommandSet>,
+    conn: TcpStream,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let write_half = conn.try_clone()?;
+
+    let mut reader = Reader::Tcp(conn);
+    let mut writer = Writer::Tcp(write_half);
+
+    if let Some(key) = &args.key {
+        reader = Reader::Ciphered(Box::new(reader), XorState::new(key));
+        writer = Writer::Ciphered(Box::new(writer), XorState::new(key));
+    }
+
+    let mut buf = vec![0u8; audio::read_buf_size(args.sample_format, args.sample_rate, args.channels)];
+    let mut decoder = audio::PcmDecoder::new(args.sample_format, args.sample_rate, args.channels);
+
+    stream::run(
+        model,
+        args,
+        commandset,
+        || match decoder.read_chunk(&mut reader, &mut buf) {
+            Ok(Some(samples)) => Some(stream::AudioChunk { samples, captured_at: None }),
+            Ok(None) => None, // peer closed the connection
+            Err(e) => {
+                eprintln!("Warning: failed to read from socket: {}", e);
+                None
+            }
+        },
+        |event| emit_event_to(&mut writer, event, &args.format),
+    )
+}
@@ -0,0 +1,273 @@
+//! Shared streaming transcription engine.
+//!
+//! Accumulates incoming 16kHz mono f32 audio, emits partial JSONL results on
+//! a timer, and — with `--vad` — endpoints each utterance on trailing
+//! silence. `--stdin`, `--mic`, and `--serve` all feed this loop through
+//! [`run`] so every input source gets identical segmentation behavior; the
+//! `emit` closure decides where transcripts go (stdout, a TCP socket, ...).
+//! With `--commands`, each transcription result is additionally scored
+//! against the loaded [`CommandSet`](crate::commands::CommandSet) and a
+//! match emitted as its own event.
+
+use crate::commands::CommandSet;
+use crate::vad::EnergyVad;
+use crate::{dsp, Args, Event, TranscriptEvent};
+use parakeet_rs::{ParakeetTDT, TimestampMode, Transcriber};
+
+/// Minimum audio duration (in samples at 16kHz) before first transcription.
+/// ~1 second — shorter audio tends to produce garbage.
+const MIN_SAMPLES_FOR_TRANSCRIPTION: usize = 16_000;
+
+/// How often to run intermediate transcription (in samples at 16kHz).
+/// ~0.5 seconds of audio between each partial result.
+/// At ~40-80ms inference on M-series chips, this gives near real-time feel
+/// with text updating roughly twice per second.
+const CHUNK_INTERVAL_SAMPLES: usize = 8_000;
+
+/// Maximum audio buffer size (in samples at 16kHz).
+/// ~3 minutes — TDT models have a ~4-5 min hard limit.
+const MAX_BUFFER_SAMPLES: usize = 16_000 * 180;
+
+/// One chunk of audio delivered to the streaming engine: always 16kHz mono
+/// f32 samples, with an optional wall-clock capture timestamp (Unix epoch
+/// seconds) when the source can provide one (e.g. `--mic`).
+pub struct AudioChunk {
+    pub samples: Vec<f32>,
+    pub captured_at: Option<f64>,
+}
+
+/// Run the accumulate/partial/endpoint/final loop over `next_chunk`, which
+/// should return `None` once the source is exhausted (EOF / stream end),
+/// handing each resulting event to `emit`. ParakeetTDT is stateless so each
+/// transcribe call is independent.
+pub fn run(
+    model: &mut ParakeetTDT,
+    args: &Args,
+    commandset: Option<&CommandSet>,
+    mut next_chunk: impl FnMut() -> Option<AudioChunk>,
+    mut emit: impl FnMut(&Event),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut all_audio: Vec<f32> = Vec::new();
+    let mut samples_since_last_transcription: usize = 0;
+    let mut last_text = String::new();
+    let mut last_captured_at: Option<f64> = None;
+
+    // `--denoise` state, carried across ticks so a long utterance denoises
+    // incrementally (not an O(n) re-denoise of the whole buffer every
+    // ~0.5s) and RNNoise's recurrent state doesn't cold-start every tick.
+    // `denoised_audio` is aligned with `all_audio` from its start but lags
+    // it by the samples the denoiser still holds back (a partial RNNoise
+    // frame and resampler context) until `flush`; it resets alongside it.
+    let mut denoiser = args.denoise.then(|| dsp::Denoiser::new(16_000));
+    let mut denoised_audio: Vec<f32> = Vec::new();
+
+    // Energy VAD for end-of-utterance endpointing. `last_ms` is the
+    // trailing window compared against the whole-buffer energy; the
+    // separate `silence_ms` flag is the hang time before we call it an
+    // endpoint, so a single short dip below threshold doesn't cut speech.
+    let vad = EnergyVad::new(16_000, args.vad_thold, args.freq_thold, 1_000);
+    let silence_hang_samples = (16_000 * args.silence_ms / 1000) as usize;
+    let mut heard_speech = false;
+    let mut silence_run_samples: usize = 0;
+
+    // Emit a "ready" event so the consumer knows audio processing has started
+    emit(&Event::Transcript(TranscriptEvent {
+        text: String::new(),
+        is_final: false,
+        duration_secs: None,
+        audio_duration_secs: Some(0.0),
+        timestamps: None,
+        captured_at: None,
+    }));
+
+    while let Some(chunk) = next_chunk() {
+        let AudioChunk { samples, captured_at } = chunk;
+        if captured_at.is_some() {
+            last_captured_at = captured_at;
+        }
+
+        all_audio.extend_from_slice(&samples);
+        samples_since_last_transcription += samples.len();
+        if let Some(d) = denoiser.as_mut() {
+            denoised_audio.extend(d.push(&samples));
+        }
+
+        // Enforce max buffer size (~3 minutes) — trim from the front
+        if all_audio.len() > MAX_BUFFER_SAMPLES {
+            let excess = all_audio.len() - MAX_BUFFER_SAMPLES;
+            all_audio.drain(..excess);
+            let denoised_excess = excess.min(denoised_audio.len());
+            denoised_audio.drain(..denoised_excess);
+            eprintln!(
+                "Warning: audio buffer exceeded 3 minutes, trimmed oldest {} samples",
+                excess
+            );
+        }
+
+        // VAD endpointing: once we've heard speech, track trailing
+        // silence and finalize the utterance once it's held long enough.
+        if args.vad && all_audio.len() >= MIN_SAMPLES_FOR_TRANSCRIPTION {
+            if vad.is_speech(&all_audio) {
+                heard_speech = true;
+                silence_run_samples = 0;
+            } else if heard_speech {
+                silence_run_samples += samples.len();
+            }
+
+            if heard_speech && silence_run_samples >= silence_hang_samples {
+                let audio_duration = all_audio.len() as f32 / 16000.0;
+                if let Some(d) = denoiser.as_mut() {
+                    denoised_audio.extend(d.flush());
+                }
+                let source = if denoiser.is_some() { &denoised_audio } else { &all_audio };
+                let preprocessed = dsp::apply_normalize(source, 16000, args.normalize, args.target_lufs);
+                match model.transcribe_samples(preprocessed, 16000, 1, Some(TimestampMode::Sentences)) {
+                    Ok(result) => {
+                        last_text = result.text.clone();
+                        emit_command_match(commandset, &result.text, true, &mut emit);
+
+                        let timestamps: Vec<crate::TimedTokenOut> = result
+                            .tokens
+                            .iter()
+                            .map(|t| crate::TimedTokenOut {
+                                word: t.text.clone(),
+                                start: t.start,
+                                end: t.end,
+                            })
+                            .collect();
+
+                        emit(&Event::Transcript(TranscriptEvent {
+                            text: result.text,
+                            is_final: true,
+                            duration_secs: None,
+                            audio_duration_secs: Some(audio_duration),
+                            timestamps: if timestamps.is_empty() { None } else { Some(timestamps) },
+                            captured_at: last_captured_at,
+                        }));
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: endpoint transcription failed: {}", e);
+                    }
+                }
+
+                all_audio.clear();
+                denoised_audio.clear();
+                if args.denoise {
+                    denoiser = Some(dsp::Denoiser::new(16_000));
+                }
+                samples_since_last_transcription = 0;
+                heard_speech = false;
+                silence_run_samples = 0;
+                continue;
+            }
+        }
+
+        // Emit partial transcription every ~0.5 seconds of new audio,
+        // but only after we have enough audio for a meaningful result
+        if samples_since_last_transcription >= CHUNK_INTERVAL_SAMPLES
+            && all_audio.len() >= MIN_SAMPLES_FOR_TRANSCRIPTION
+        {
+            samples_since_last_transcription = 0;
+            let audio_duration = all_audio.len() as f32 / 16000.0;
+
+            let source = if denoiser.is_some() { &denoised_audio } else { &all_audio };
+            let preprocessed = dsp::apply_normalize(source, 16000, args.normalize, args.target_lufs);
+            match model.transcribe_samples(
+                preprocessed,
+                16000,
+                1,
+                None, // Skip timestamps for partial results (faster)
+            ) {
+                Ok(result) => {
+                    last_text = result.text.clone();
+                    emit_command_match(commandset, &result.text, false, &mut emit);
+                    emit(&Event::Transcript(TranscriptEvent {
+                        text: result.text,
+                        is_final: false,
+                        duration_secs: None,
+                        audio_duration_secs: Some(audio_duration),
+                        timestamps: None,
+                        captured_at: last_captured_at,
+                    }));
+                }
+                Err(e) => {
+                    eprintln!("Warning: partial transcription failed: {}", e);
+                    // Continue accumulating audio — don't abort
+                }
+            }
+        }
+    }
+
+    // ── Final transcription once the source is exhausted ──────────
+    if all_audio.is_empty() {
+        emit(&Event::Transcript(TranscriptEvent {
+            text: String::new(),
+            is_final: true,
+            duration_secs: Some(0.0),
+            audio_duration_secs: Some(0.0),
+            timestamps: None,
+            captured_at: None,
+        }));
+    } else {
+        let final_start = std::time::Instant::now();
+        if let Some(d) = denoiser.as_mut() {
+            denoised_audio.extend(d.flush());
+        }
+        let source = if denoiser.is_some() { &denoised_audio } else { &all_audio };
+        let preprocessed = dsp::apply_normalize(source, 16000, args.normalize, args.target_lufs);
+        match model.transcribe_samples(preprocessed, 16000, 1, Some(TimestampMode::Sentences)) {
+            Ok(result) => {
+                let elapsed = final_start.elapsed().as_secs_f32();
+                let audio_duration = all_audio.len() as f32 / 16000.0;
+
+                let timestamps: Vec<crate::TimedTokenOut> = result
+                    .tokens
+                    .iter()
+                    .map(|t| crate::TimedTokenOut {
+                        word: t.text.clone(),
+                        start: t.start,
+                        end: t.end,
+                    })
+                    .collect();
+
+                emit_command_match(commandset, &result.text, true, &mut emit);
+                emit(&Event::Transcript(TranscriptEvent {
+                    text: result.text,
+                    is_final: true,
+                    duration_secs: Some(elapsed),
+                    audio_duration_secs: Some(audio_duration),
+                    timestamps: if timestamps.is_empty() { None } else { Some(timestamps) },
+                    captured_at: last_captured_at,
+                }));
+            }
+            Err(e) => {
+                // If final transcription fails, emit last known good text
+                eprintln!("Error: final transcription failed: {}", e);
+                if !last_text.is_empty() {
+                    emit_command_match(commandset, &last_text, true, &mut emit);
+                    emit(&Event::Transcript(TranscriptEvent {
+                        text: last_text,
+                        is_final: true,
+                        duration_secs: None,
+                        audio_duration_secs: None,
+                        timestamps: None,
+                        captured_at: last_captured_at,
+                    }));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// If a command set is loaded, score `text` against it and emit a command
+/// event for the best match above `--command-thold`, if any. A no-op when
+/// `--commands` isn't set.
+fn emit_command_match(commandset: Option<&CommandSet>, text: &str, is_final: bool, emit: &mut impl FnMut(&Event)) {
+    if let Some(cs) = commandset {
+        if let Some((command, score)) = cs.best_match(text) {
+            emit(&Event::Command(crate::commands::CommandEvent { command: command.to_string(), score, is_final }));
+        }
+    }
+}
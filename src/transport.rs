@@ -0,0 +1,106 @@
+//! Transport-agnostic byte source/sink, following lonelyradio's design:
+//! the transcription loop talks to a [`Reader`]/[`Writer`] enum instead of
+//! a concrete stdin or socket type, so `--serve` (and any future protocol)
+//! plugs into the same code path as `--stdin`. The `Ciphered` variant
+//! optionally wraps either side with a symmetric XOR stream for `--key`.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// A PCM byte source: stdin today, a TCP connection under `--serve`.
+pub enum Reader {
+    Stdin(io::Stdin),
+    Tcp(TcpStream),
+    Ciphered(Box<Reader>, XorState),
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Reader::Stdin(s) => s.read(buf),
+            Reader::Tcp(s) => s.read(buf),
+            Reader::Ciphered(inner, state) => {
+                let n = inner.read(buf)?;
+                state.xor_in_place(&mut buf[..n]);
+                Ok(n)
+            }
+        }
+    }
+}
+
+/// A JSONL transcript sink: stdout today, a TCP connection under `--serve`.
+pub enum Writer {
+    Stdout(io::Stdout),
+    Tcp(TcpStream),
+    Ciphered(Box<Writer>, XorState),
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Writer::Stdout(s) => s.write(buf),
+            Writer::Tcp(s) => s.write(buf),
+            Writer::Ciphered(inner, state) => {
+                // `inner.write` may only consume a prefix of `buf` (a short
+                // write); `write_all` then retries with the remainder, so
+                // the keystream position must only advance by the bytes
+                // actually written, not by all of `buf.len()`.
+                let xored = state.xor_copy(buf);
+                let n = inner.write(&xored)?;
+                state.advance(n);
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Writer::Stdout(s) => s.flush(),
+            Writer::Tcp(s) => s.flush(),
+            Writer::Ciphered(inner, _) => inner.flush(),
+        }
+    }
+}
+
+/// Symmetric XOR stream cipher state for lightweight obfuscation over
+/// untrusted links (`--key`). This is NOT real encryption — a repeating-key
+/// XOR keystream is trivially recovered from known plaintext — just enough
+/// to stop casual inspection of PCM/JSONL on the wire.
+pub struct XorState {
+    key: Vec<u8>,
+    pos: usize,
+}
+
+impl XorState {
+    pub fn new(key: &str) -> Self {
+        Self { key: key.as_bytes().to_vec(), pos: 0 }
+    }
+
+    fn xor_in_place(&mut self, buf: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        for b in buf.iter_mut() {
+            *b ^= self.key[self.pos % self.key.len()];
+            self.pos += 1;
+        }
+    }
+
+    /// XOR `buf` against the keystream starting at the current position
+    /// without advancing it. Pair with `advance` once the caller knows how
+    /// many of the returned bytes were actually consumed.
+    fn xor_copy(&self, buf: &[u8]) -> Vec<u8> {
+        if self.key.is_empty() {
+            return buf.to_vec();
+        }
+        buf.iter()
+            .enumerate()
+            .map(|(i, &b)| b ^ self.key[(self.pos + i) % self.key.len()])
+            .collect()
+    }
+
+    /// Advance the keystream position by `n` bytes actually consumed.
+    fn advance(&mut self, n: usize) {
+        self.pos += n;
+    }
+}
@@ -0,0 +1,79 @@
+//! Energy-based voice activity detection for endpointing streaming audio.
+//!
+//! This mirrors the simple VAD used by the whisper.cpp stream / LSP examples:
+//! compare the energy of a trailing window of audio against the energy of
+//! the whole recent buffer, optionally after a one-pole high-pass filter to
+//! reject low-frequency rumble. It is intentionally crude (no training, no
+//! spectral features) but cheap enough to run on every incoming chunk.
+
+/// Simple energy-ratio VAD: speech is present while the trailing window is
+/// "louder" than the buffer as a whole.
+pub struct EnergyVad {
+    vad_thold: f32,
+    freq_thold: f32,
+    last_ms: usize,
+    sample_rate: usize,
+}
+
+impl EnergyVad {
+    pub fn new(sample_rate: usize, vad_thold: f32, freq_thold: f32, last_ms: usize) -> Self {
+        Self {
+            vad_thold,
+            freq_thold,
+            last_ms,
+            sample_rate,
+        }
+    }
+
+    /// Returns true if the trailing `last_ms` of `samples` looks like speech
+    /// relative to the energy of the whole slice.
+    pub fn is_speech(&self, samples: &[f32]) -> bool {
+        if samples.is_empty() {
+            return false;
+        }
+
+        let filtered;
+        let samples = if self.freq_thold > 0.0 {
+            filtered = high_pass_filter(samples, self.freq_thold, self.sample_rate);
+            &filtered[..]
+        } else {
+            samples
+        };
+
+        let last_n = (self.sample_rate * self.last_ms / 1000).min(samples.len());
+        if last_n == 0 {
+            return false;
+        }
+
+        let energy_all = mean_abs(samples);
+        if energy_all <= f32::EPSILON {
+            return false;
+        }
+
+        let energy_last = mean_abs(&samples[samples.len() - last_n..]);
+        energy_last > self.vad_thold * energy_all
+    }
+}
+
+fn mean_abs(samples: &[f32]) -> f32 {
+    samples.iter().map(|s| s.abs()).sum::<f32>() / samples.len() as f32
+}
+
+/// One-pole high-pass filter to reject rumble below `cutoff_hz`.
+fn high_pass_filter(samples: &[f32], cutoff_hz: f32, sample_rate: usize) -> Vec<f32> {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff_hz);
+    let dt = 1.0 / sample_rate as f32;
+    let alpha = rc / (rc + dt);
+
+    let mut out = Vec::with_capacity(samples.len());
+    let mut y_prev = 0.0f32;
+    let mut x_prev = samples[0];
+    out.push(samples[0]);
+    for &x in &samples[1..] {
+        let y = alpha * (y_prev + x - x_prev);
+        out.push(y);
+        y_prev = y;
+        x_prev = x;
+    }
+    out
+}